@@ -1,16 +1,15 @@
+mod tetris_logic;
+
 use winit::event::{ Event, WindowEvent };
 use winit::event_loop::{ ControlFlow, EventLoop };
 use winit::window::WindowBuilder;
-use winit::keyboard::{ Key::Named, NamedKey };
+use winit::keyboard::{ Key::{ Named, Character }, NamedKey };
 use tiny_skia::{ FillRule, Paint, PathBuilder, Pixmap, Rect, Transform };
-use std::time::{ Duration, SystemTime };
+use tetris_logic::{ Command, Tetris, Tetromino, BOARD_WIDTH, BOARD_HEIGHT };
 
 const UNIT_SIZE: i32 = 20;
-const BOARD_WIDTH: i32 = 10;
-const BOARD_HEIGHT: i32 = 22;
-
-/// Type of the key.
-enum Key { LEFT, RIGHT, UP, DOWN, SP, OTHER, }
+/// Width of the side panel that previews the next and held pieces.
+const PANEL_WIDTH: i32 = 6 * UNIT_SIZE;
 
 fn main() {
 
@@ -18,7 +17,7 @@ fn main() {
     event_loop.set_control_flow(ControlFlow::Poll);
 
     let window = WindowBuilder::new()
-        .with_inner_size(winit::dpi::LogicalSize::new(BOARD_WIDTH * UNIT_SIZE, BOARD_HEIGHT * UNIT_SIZE))
+        .with_inner_size(winit::dpi::LogicalSize::new(BOARD_WIDTH * UNIT_SIZE + PANEL_WIDTH, BOARD_HEIGHT * UNIT_SIZE))
         .with_title("Tetris")
         .build(&event_loop).unwrap();
 
@@ -26,7 +25,7 @@ fn main() {
     let context = softbuffer::Context::new(window.clone()).unwrap();
     let mut surface = softbuffer::Surface::new(&context, window.clone()).unwrap();
 
-    let mut game: Tetris = Tetris::new();
+    let mut game = Tetris::new();
 
     let _ = event_loop.run(move |event, elwt| {
         match event {
@@ -36,20 +35,24 @@ fn main() {
                 ..
             } if event.state.is_pressed() => {
                 match event.logical_key {
-                    Named(NamedKey::ArrowRight) => game.key_pressed(Key::RIGHT),
-                    Named(NamedKey::ArrowLeft)  => game.key_pressed(Key::LEFT),
-                    Named(NamedKey::ArrowDown)  => game.key_pressed(Key::DOWN),
-                    Named(NamedKey::ArrowUp)    => game.key_pressed(Key::UP),
-                    Named(NamedKey::Space)      => game.key_pressed(Key::SP),
+                    Named(NamedKey::ArrowRight) => game.apply(Command::Right),
+                    Named(NamedKey::ArrowLeft)  => game.apply(Command::Left),
+                    Named(NamedKey::ArrowDown)  => game.apply(Command::RotateCcw),
+                    Named(NamedKey::ArrowUp)    => game.apply(Command::RotateCw),
+                    Named(NamedKey::Space)      => game.apply(Command::HardDrop),
                     Named(NamedKey::Escape)     => game.rerun(),
-                    _ => game.key_pressed(Key::OTHER),
+                    Character(ref c) if c.as_str() == "c" => game.apply(Command::Hold),
+                    _ => game.apply(Command::SoftDrop),
                 };
                 window.request_redraw();
             },
             Event::AboutToWait => {
-                if !game.stopped {
+                if !game.is_stopped() {
                     game.tick();
-                    window.set_title(format!("Tetris:{}", game.score).as_str());
+                    window.set_title(format!(
+                        "Tetris:{} Lv{} Best:{}",
+                        game.score(), game.level(), game.best_score(),
+                    ).as_str());
                     window.request_redraw();
                 }
             },
@@ -66,7 +69,7 @@ fn main() {
                 ).unwrap();
 
                 let mut pixmap = Pixmap::new(width, height).unwrap();
-                game.draw(&mut pixmap);
+                draw(&game, &mut pixmap);
                 let mut buffer = surface.buffer_mut().unwrap();
                 for index in 0..(width * height) as usize {
                     buffer[index] =
@@ -81,267 +84,139 @@ fn main() {
     });
 }
 
-/// Tetromino is a geometric shape composed of four squares, connected orthogonally.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Tetromino { S, Z, I, T, O, J, L, X, }
-
-impl Tetromino {
-    fn rand() -> Self {
-        match rand::random::<u32>() % 7 {
-            0 => Tetromino::S, 1 => Tetromino::Z,
-            2 => Tetromino::I, 3 => Tetromino::T,
-            4 => Tetromino::O, 5 => Tetromino::J,
-            6 => Tetromino::L, _ => Tetromino::X,
+fn draw(game: &Tetris, pixmap: &mut Pixmap) {
+    for y in 0..BOARD_HEIGHT {
+        for x in 0..BOARD_WIDTH {
+            draw_square(pixmap, x, y, game.board_cell(x, y));
         }
     }
-
-    fn shape(&self) -> [[i32; 2]; 4] {
-        match self {
-            Tetromino::S => [[ 0, -1], [0,  0], [-1, 0], [-1,  1]],
-            Tetromino::Z => [[ 0, -1], [0,  0], [ 1, 0], [ 1,  1]],
-            Tetromino::I => [[ 0, -1], [0,  0], [ 0, 1], [ 0,  2]],
-            Tetromino::T => [[-1,  0], [0,  0], [ 1, 0], [ 0, -1]],
-            Tetromino::O => [[ 0,  0], [1,  0], [ 0, 1], [ 1,  1]],
-            Tetromino::J => [[-1, -1], [0, -1], [ 0, 0], [ 0,  1]],
-            Tetromino::L => [[ 1, -1], [0, -1], [ 0, 0], [ 0,  1]],
-            Tetromino::X => [[0; 2]; 4],
+    if game.current_kind() != Tetromino::X {
+        for (x, y) in game.ghost_cells() {
+            draw_square_alpha(pixmap, x, y, game.current_kind(), 60);
         }
     }
-
-    fn color(&self) -> (u8, u8, u8) {
-        match self {
-            Tetromino::S => (204, 102, 102),
-            Tetromino::Z => (102, 204, 102),
-            Tetromino::I => (104, 102, 204),
-            Tetromino::T => (204, 204, 102),
-            Tetromino::O => (204, 102, 204),
-            Tetromino::J => (204, 204, 204),
-            Tetromino::L => (218, 170,   0),
-            _            => (  0,   0,   0)
-        }
+    for (x, y) in game.current_cells() {
+        draw_square(pixmap, x, y, game.current_kind());
     }
+    draw_panel(game, pixmap);
 }
 
-/// A Tetromino block.
-#[derive(Copy, Clone, Debug)]
-struct Block {
-    kind: Tetromino,
-    points: [[i32; 2]; 4],
-    x: i32, y: i32,
-}
-
-impl Block {
-
-    fn new(x: i32, y: i32) -> Self {
-        let kind = Tetromino::rand();
-        Block {
-            kind,
-            points: kind.shape(),
-            x,
-            y: y  - kind.shape().iter().max_by_key(|p| p[1]).unwrap()[1],
-        }
-    }
-
-    fn empty() -> Self {
-        let kind = Tetromino::X;
-        Block { kind, points: kind.shape(), x: 0, y: 0 }
-    }
-
-    fn is_empty(&self) -> bool {
-        self.kind == Tetromino::X
-    }
-
-    fn point(&self, i: usize) -> (i32, i32) {
-        (self.x + self.points[i][0], self.y + self.points[i][1])
+/// Draws the hold slot, the upcoming pieces, and the high-score table
+/// in the side panel.
+fn draw_panel(game: &Tetris, pixmap: &mut Pixmap) {
+    let panel_x = BOARD_WIDTH * UNIT_SIZE + 2 * UNIT_SIZE;
+    if let Some(kind) = game.hold() {
+        draw_piece_preview(pixmap, kind, panel_x, 2 * UNIT_SIZE);
     }
-
-    fn left(&self)  -> Block { Block { x: self.x - 1, ..*self } }
-    fn right(&self) -> Block { Block { x: self.x + 1, ..*self } }
-    fn down(&self)  -> Block { Block { y: self.y - 1, ..*self } }
-
-    fn rotate_left(&self)  -> Block { self.rotate(false) }
-    fn rotate_right(&self) -> Block { self.rotate(true) }
-
-    fn rotate(&self, clockwise: bool) -> Block {
-        let mut points: [[i32; 2]; 4] = [[0; 2]; 4];
-        for i in 0..4 {
-            points[i] = if clockwise {
-                [-self.points[i][1], self.points[i][0]]
-            } else {
-                [self.points[i][1], -self.points[i][0]]
-            };
-        }
-        Block { points, ..*self }
+    for (i, kind) in game.next().iter().enumerate() {
+        draw_piece_preview(pixmap, *kind, panel_x, (6 + i as i32 * 4) * UNIT_SIZE);
     }
-
-}
-
-
-fn index_at(x: i32, y: i32) -> usize {
-    (y * BOARD_WIDTH + x) as usize
+    draw_high_scores(game, pixmap);
 }
 
-/// Game of tetris.
-struct Tetris {
-    board: [Tetromino; (BOARD_WIDTH  * BOARD_HEIGHT) as usize],
-    current: Block,
-    stopped: bool,
-    time: SystemTime,
-    score: u32,
-}
-
-impl Tetris {
-
-    fn new() -> Self {
-        Tetris {
-            board: [Tetromino::X; (BOARD_WIDTH  * BOARD_HEIGHT) as usize],
-            current: Block::empty(),
-            stopped: false,
-            time: SystemTime::now(),
-            score: 0,
-        }
-    }
+/// High-score table: each entry's score rendered as digits, dimmer the
+/// further down the table it sits.
+fn draw_high_scores(game: &Tetris, pixmap: &mut Pixmap) {
+    const ROW_HEIGHT: i32 = DIGIT_HEIGHT + DIGIT_PIXEL;
 
-    fn rerun(&mut self) {
-        self.board = [Tetromino::X; (BOARD_WIDTH  * BOARD_HEIGHT) as usize];
-        self.current = Block::empty();
-        self.stopped = false;
-        self.time = SystemTime::now();
-        self.score = 0;
-    }
+    let scores = game.high_scores();
+    let panel_x = BOARD_WIDTH * UNIT_SIZE + 2 * UNIT_SIZE;
+    let top = 18 * UNIT_SIZE;
 
-    fn tick(&mut self) {
-        if self.current.is_empty() {
-            self.put_block();
-        } else if self.time.elapsed().unwrap() > Duration::from_millis((1000 - self.score) as u64) {
-            self.down();
-            self.time = SystemTime::now();
-        }
-    }
-
-    fn key_pressed(&mut self, key: Key) {
-        if self.stopped || self.current.is_empty() {
-            return;
-        }
-        match key {
-            Key::LEFT  => { self.try_move(self.current.left()); },
-            Key::RIGHT => { self.try_move(self.current.right()); },
-            Key::UP    => { self.try_move(self.current.rotate_right()); },
-            Key::DOWN  => { self.try_move(self.current.rotate_left()); },
-            Key::OTHER => { self.down(); },
-            Key::SP    => { self.drop_down(); },
-        };
+    for (i, &score) in scores.iter().enumerate() {
+        let y = top + i as i32 * ROW_HEIGHT;
+        let alpha = 255 - (i as u32 * 180 / scores.len().max(1) as u32) as u8;
+        draw_number(pixmap, score, panel_x, y, (200, 200, 200), alpha);
     }
+}
 
-    fn down(&mut self) {
-        if !self.try_move(self.current.down()) {
-            self.block_dropped();
-        }
+/// Pixel size of one dot in [`DIGIT_GLYPHS`].
+const DIGIT_PIXEL: i32 = 2;
+const DIGIT_WIDTH: i32 = 3 * DIGIT_PIXEL;
+const DIGIT_HEIGHT: i32 = 5 * DIGIT_PIXEL;
+
+/// A minimal 3x5 bitmap font for the digits 0-9 — just enough to render
+/// the high-score table, since no text-rendering library is available.
+const DIGIT_GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b001, 0b001, 0b001], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+];
+
+/// Draws `number`'s decimal digits left-to-right, starting at `(x, y)`.
+fn draw_number(pixmap: &mut Pixmap, number: u32, x: i32, y: i32, color: (u8, u8, u8), alpha: u8) {
+    for (i, digit) in number.to_string().chars().enumerate() {
+        let digit = digit.to_digit(10).unwrap();
+        draw_digit(pixmap, digit, x + i as i32 * (DIGIT_WIDTH + DIGIT_PIXEL), y, color, alpha);
     }
+}
 
-    fn drop_down(&mut self) {
-        while self.current.y > 0 {
-            if !self.try_move(self.current.down()) {
-                break;
+/// Draws one digit glyph with its top-left corner at `(x, y)`.
+fn draw_digit(pixmap: &mut Pixmap, digit: u32, x: i32, y: i32, color: (u8, u8, u8), alpha: u8) {
+    for (row, bits) in DIGIT_GLYPHS[digit as usize].iter().enumerate() {
+        for col in 0..3 {
+            if bits & (1 << (2 - col)) != 0 {
+                fill_bar(
+                    pixmap, x + col * DIGIT_PIXEL, y + row as i32 * DIGIT_PIXEL,
+                    DIGIT_PIXEL, DIGIT_PIXEL, color, alpha,
+                );
             }
         }
-        self.block_dropped();
-    }
-
-    fn block_dropped(&mut self) {
-        for i in 0..4 {
-            let (x, y) = self.current.point(i);
-            self.board[index_at(x, y)] = self.current.kind;
-        }
-        self.remove_complete_lines();
-        if self.current.is_empty() {
-            self.put_block();
-        }
     }
+}
 
-    fn put_block(&mut self) {
-        self.stopped = !self.try_move(Block::new(BOARD_WIDTH / 2, BOARD_HEIGHT - 1));
+/// Draws the four cells of `kind`'s spawn shape with its top-left
+/// corner pinned at pixel coordinates `(origin_x, origin_y)`.
+fn draw_piece_preview(pixmap: &mut Pixmap, kind: Tetromino, origin_x: i32, origin_y: i32) {
+    if kind == Tetromino::X {
+        return;
     }
-
-    fn try_move(&mut self, block: Block) -> bool {
-        for i in 0..4 {
-            let (x, y) = block.point(i);
-            if x < 0 || x >= BOARD_WIDTH || y < 0 || y >= BOARD_HEIGHT {
-                return false
-            }
-            if self.board[index_at(x, y)] != Tetromino::X {
-                return false
-            }
-        }
-        self.current = block;
-        true
+    for [cx, cy] in kind.shape() {
+        let x = origin_x + (cx + 1) * UNIT_SIZE;
+        let y = origin_y - cy * UNIT_SIZE;
+        fill_square(pixmap, x, y, kind, 255);
     }
+}
 
-    fn remove_complete_lines(&mut self) {
-        let mut line_count = 0;
-
-        for y in (0..BOARD_HEIGHT).rev() {
-            let mut complete = true;
-            for x in 0.. BOARD_WIDTH {
-                if self.board[index_at(x, y)] == Tetromino::X {
-                    // traverse the rows and if there is a blank, it cannot be completed
-                    complete = false;
-                    break
-                }
-            }
-            if complete {
-                line_count += 1;
-                // drop the line above the completed line
-                for dy in y..BOARD_HEIGHT - 1 {
-                    for x in 0..BOARD_WIDTH {
-                        // copy from the above line
-                        self.board[index_at(x, dy)] = self.board[index_at(x, dy + 1)];
-                    }
-                }
-            }
-        }
-        self.score += line_count * line_count;
-        self.current = Block::empty();
-    }
+fn draw_square(pixmap: &mut Pixmap, x: i32, y: i32, kind: Tetromino) {
+    draw_square_alpha(pixmap, x, y, kind, 255);
+}
 
-    fn draw(&self, pixmap: &mut Pixmap) {
-        for y in 0..BOARD_HEIGHT {
-            for x in 0..BOARD_WIDTH {
-                Tetris::draw_square(pixmap, x, y,self.board[index_at(x, y)]);
-            }
-        }
-        for i in 0..4 {
-            let (x, y) = self.current.point(i);
-            Tetris::draw_square(pixmap, x, y, self.current.kind);
-        }
+fn draw_square_alpha(pixmap: &mut Pixmap, x: i32, y: i32, kind: Tetromino, alpha: u8) {
+    if kind == Tetromino::X {
+        return;
     }
 
-    fn draw_square(pixmap: &mut Pixmap, x: i32, y: i32, kind: Tetromino) {
-        if kind == Tetromino::X {
-            return;
-        }
-
-        // left-bottom to top-left
-        let x = x * UNIT_SIZE;
-        let y = (BOARD_HEIGHT - 1 - y) * UNIT_SIZE;
+    // left-bottom to top-left
+    let x = x * UNIT_SIZE;
+    let y = (BOARD_HEIGHT - 1 - y) * UNIT_SIZE;
+    fill_square(pixmap, x, y, kind, alpha);
+}
 
-        let rect = Rect::from_xywh(
-            (x + 1) as f32,
-            (y + 1) as f32,
-            (UNIT_SIZE - 2) as f32,
-            (UNIT_SIZE - 2) as f32,
-        ).unwrap();
-        let path = PathBuilder::from_rect(rect);
-        let mut paint = Paint::default();
-        let (r ,g, b) = kind.color();
-        paint.set_color_rgba8(r, g, b, 255);
-        pixmap.fill_path(
-            &path,
-            &paint,
-            FillRule::EvenOdd,
-            Transform::identity(),
-            None,
-        );
-    }
+/// Fills one unit square whose top-left corner is at pixel `(x, y)`.
+fn fill_square(pixmap: &mut Pixmap, x: i32, y: i32, kind: Tetromino, alpha: u8) {
+    fill_bar(pixmap, x + 1, y + 1, UNIT_SIZE - 2, UNIT_SIZE - 2, kind.color(), alpha);
 }
 
+/// Fills a `width` x `height` rectangle whose top-left corner is at pixel `(x, y)`.
+fn fill_bar(pixmap: &mut Pixmap, x: i32, y: i32, width: i32, height: i32, color: (u8, u8, u8), alpha: u8) {
+    let rect = Rect::from_xywh(x as f32, y as f32, width as f32, height as f32).unwrap();
+    let path = PathBuilder::from_rect(rect);
+    let mut paint = Paint::default();
+    let (r, g, b) = color;
+    paint.set_color_rgba8(r, g, b, alpha);
+    pixmap.fill_path(
+        &path,
+        &paint,
+        FillRule::EvenOdd,
+        Transform::identity(),
+        None,
+    );
+}
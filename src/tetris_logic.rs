@@ -0,0 +1,555 @@
+//! Tetris game rules: the board, pieces, rotation, and scoring. This
+//! module has no rendering or windowing dependencies — `main` feeds
+//! `Command`s in via `Tetris::apply` and reads state back out through
+//! the accessors below to draw it.
+
+use std::time::{ Duration, SystemTime };
+
+pub const BOARD_WIDTH: i32 = 10;
+pub const BOARD_HEIGHT: i32 = 22;
+/// Number of upcoming pieces shown in the preview queue.
+pub const NEXT_PREVIEW: usize = 3;
+/// Lines that must be cleared to advance one level.
+const LINES_PER_LEVEL: u32 = 10;
+/// Local file the top scores are persisted to between runs.
+const HIGH_SCORE_FILE: &str = "tetris_highscores.txt";
+/// How many scores the high-score table keeps.
+const HIGH_SCORE_COUNT: usize = 10;
+/// How long a grounded piece may sit before it locks in place.
+const LOCK_DELAY: Duration = Duration::from_millis(500);
+
+/// An input the game understands, independent of any particular keyboard layout.
+pub enum Command { Left, Right, RotateCw, RotateCcw, SoftDrop, HardDrop, Hold }
+
+/// Milliseconds of gravity per cell at `level`, decreasing with level
+/// and clamped to a minimum so it can never reach zero or go negative.
+fn gravity_millis(level: u32) -> u64 {
+    const TABLE: [u64; 15] = [
+        1000, 793, 618, 473, 355, 262, 190, 135, 94, 64, 43, 28, 18, 11, 7,
+    ];
+    let step = TABLE[(level as usize).min(TABLE.len() - 1)];
+    step.max(50)
+}
+
+/// Loads the persisted high scores, highest first. Missing or unreadable
+/// files are treated as an empty table.
+fn load_high_scores() -> Vec<u32> {
+    std::fs::read_to_string(HIGH_SCORE_FILE)
+        .map(|content| content.lines().filter_map(|line| line.trim().parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Inserts `score` into `scores`, keeping it sorted highest-first and
+/// truncated to `HIGH_SCORE_COUNT`, then writes it back to disk.
+fn save_high_score(scores: &mut Vec<u32>, score: u32) {
+    scores.push(score);
+    scores.sort_unstable_by(|a, b| b.cmp(a));
+    scores.truncate(HIGH_SCORE_COUNT);
+    let content = scores.iter().map(u32::to_string).collect::<Vec<_>>().join("\n");
+    let _ = std::fs::write(HIGH_SCORE_FILE, content);
+}
+
+/// Tetromino is a geometric shape composed of four squares, connected orthogonally.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tetromino { S, Z, I, T, O, J, L, X, }
+
+impl Tetromino {
+    pub fn shape(&self) -> [[i32; 2]; 4] {
+        match self {
+            Tetromino::S => [[ 0, -1], [0,  0], [-1, 0], [-1,  1]],
+            Tetromino::Z => [[ 0, -1], [0,  0], [ 1, 0], [ 1,  1]],
+            Tetromino::I => [[ 0, -1], [0,  0], [ 0, 1], [ 0,  2]],
+            Tetromino::T => [[-1,  0], [0,  0], [ 1, 0], [ 0, -1]],
+            Tetromino::O => [[ 0,  0], [1,  0], [ 0, 1], [ 1,  1]],
+            Tetromino::J => [[-1, -1], [0, -1], [ 0, 0], [ 0,  1]],
+            Tetromino::L => [[ 1, -1], [0, -1], [ 0, 0], [ 0,  1]],
+            Tetromino::X => [[0; 2]; 4],
+        }
+    }
+
+    pub fn color(&self) -> (u8, u8, u8) {
+        match self {
+            Tetromino::S => (204, 102, 102),
+            Tetromino::Z => (102, 204, 102),
+            Tetromino::I => (104, 102, 204),
+            Tetromino::T => (204, 204, 102),
+            Tetromino::O => (204, 102, 204),
+            Tetromino::J => (204, 204, 204),
+            Tetromino::L => (218, 170,   0),
+            _            => (  0,   0,   0)
+        }
+    }
+}
+
+/// A 7-bag randomizer: shuffles one of each tetromino into a bag and
+/// hands them out one at a time, refilling and reshuffling once it runs
+/// dry. Guarantees every piece appears exactly once per seven spawns.
+struct PieceBag {
+    queue: Vec<Tetromino>,
+}
+
+impl PieceBag {
+    fn new() -> Self {
+        let mut bag = PieceBag { queue: Vec::new() };
+        bag.refill();
+        bag
+    }
+
+    fn refill(&mut self) {
+        use rand::seq::SliceRandom;
+        let mut pieces = [
+            Tetromino::S, Tetromino::Z, Tetromino::I, Tetromino::T,
+            Tetromino::O, Tetromino::J, Tetromino::L,
+        ];
+        pieces.shuffle(&mut rand::thread_rng());
+        self.queue.extend_from_slice(&pieces);
+    }
+
+    fn next(&mut self) -> Tetromino {
+        if self.queue.is_empty() {
+            self.refill();
+        }
+        self.queue.pop().unwrap()
+    }
+}
+
+/// A Tetromino block.
+#[derive(Copy, Clone, Debug)]
+struct Block {
+    kind: Tetromino,
+    points: [[i32; 2]; 4],
+    x: i32, y: i32,
+    /// Rotation state, one of 0 (spawn), 1 (R), 2 (2), 3 (L).
+    rotation: i32,
+}
+
+impl Block {
+
+    fn new(kind: Tetromino, x: i32, y: i32) -> Self {
+        Block {
+            kind,
+            points: kind.shape(),
+            x,
+            y: y  - kind.shape().iter().max_by_key(|p| p[1]).unwrap()[1],
+            rotation: 0,
+        }
+    }
+
+    fn empty() -> Self {
+        let kind = Tetromino::X;
+        Block { kind, points: kind.shape(), x: 0, y: 0, rotation: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.kind == Tetromino::X
+    }
+
+    fn point(&self, i: usize) -> (i32, i32) {
+        (self.x + self.points[i][0], self.y + self.points[i][1])
+    }
+
+    fn left(&self)  -> Block { Block { x: self.x - 1, ..*self } }
+    fn right(&self) -> Block { Block { x: self.x + 1, ..*self } }
+    fn down(&self)  -> Block { Block { y: self.y - 1, ..*self } }
+
+    fn offset(&self, dx: i32, dy: i32) -> Block {
+        Block { x: self.x + dx, y: self.y + dy, ..*self }
+    }
+
+    fn rotate_left(&self)  -> Block { self.rotate(false) }
+    fn rotate_right(&self) -> Block { self.rotate(true) }
+
+    fn rotate(&self, clockwise: bool) -> Block {
+        let mut points: [[i32; 2]; 4] = [[0; 2]; 4];
+        for i in 0..4 {
+            points[i] = if clockwise {
+                [-self.points[i][1], self.points[i][0]]
+            } else {
+                [self.points[i][1], -self.points[i][0]]
+            };
+        }
+        let rotation = if clockwise {
+            (self.rotation + 1) % 4
+        } else {
+            (self.rotation + 3) % 4
+        };
+        Block { points, rotation, ..*self }
+    }
+
+}
+
+/// The five SRS wall-kick offsets (dx, dy) to test, in order, for the
+/// rotation transition from `from` to `to` (states 0, R=1, 2, L=3).
+fn wall_kicks(kind: Tetromino, from: i32, to: i32) -> [(i32, i32); 5] {
+    match kind {
+        Tetromino::O => [(0, 0); 5],
+        Tetromino::I => match (from, to) {
+            (0, 1) => [(0, 0), (-2, 0), ( 1, 0), (-2, -1), ( 1,  2)],
+            (1, 0) => [(0, 0), ( 2, 0), (-1, 0), ( 2,  1), (-1, -2)],
+            (1, 2) => [(0, 0), (-1, 0), ( 2, 0), (-1,  2), ( 2, -1)],
+            (2, 1) => [(0, 0), ( 1, 0), (-2, 0), ( 1, -2), (-2,  1)],
+            (2, 3) => [(0, 0), ( 2, 0), (-1, 0), ( 2,  1), (-1, -2)],
+            (3, 2) => [(0, 0), (-2, 0), ( 1, 0), (-2, -1), ( 1,  2)],
+            (3, 0) => [(0, 0), ( 1, 0), (-2, 0), ( 1, -2), (-2,  1)],
+            (0, 3) => [(0, 0), (-1, 0), ( 2, 0), (-1,  2), ( 2, -1)],
+            _ => [(0, 0); 5],
+        },
+        _ => match (from, to) {
+            (0, 1) => [(0, 0), (-1, 0), (-1,  1), (0, -2), (-1, -2)],
+            (1, 0) => [(0, 0), ( 1, 0), ( 1, -1), (0,  2), ( 1,  2)],
+            (1, 2) => [(0, 0), ( 1, 0), ( 1, -1), (0,  2), ( 1,  2)],
+            (2, 1) => [(0, 0), (-1, 0), (-1,  1), (0, -2), (-1, -2)],
+            (2, 3) => [(0, 0), ( 1, 0), ( 1,  1), (0, -2), ( 1, -2)],
+            (3, 2) => [(0, 0), (-1, 0), (-1, -1), (0,  2), (-1,  2)],
+            (3, 0) => [(0, 0), (-1, 0), (-1, -1), (0,  2), (-1,  2)],
+            (0, 3) => [(0, 0), ( 1, 0), ( 1,  1), (0, -2), ( 1, -2)],
+            _ => [(0, 0); 5],
+        },
+    }
+}
+
+fn index_at(x: i32, y: i32) -> usize {
+    (y * BOARD_WIDTH + x) as usize
+}
+
+/// Game of tetris.
+pub struct Tetris {
+    board: [Tetromino; (BOARD_WIDTH  * BOARD_HEIGHT) as usize],
+    current: Block,
+    stopped: bool,
+    time: SystemTime,
+    score: u32,
+    /// Current level, increasing every `LINES_PER_LEVEL` cleared lines.
+    level: u32,
+    /// Total lines cleared this game.
+    lines_cleared: u32,
+    /// Persisted top `HIGH_SCORE_COUNT` scores, highest first.
+    high_scores: Vec<u32>,
+    bag: PieceBag,
+    /// Upcoming pieces, always kept filled to `NEXT_PREVIEW` long.
+    next: Vec<Tetromino>,
+    /// The piece set aside by a hold, if any.
+    hold: Option<Tetromino>,
+    /// Whether hold has already been used for the current piece.
+    hold_used: bool,
+    /// When the piece first became grounded, starting its lock delay.
+    /// Cleared whenever it's no longer grounded or it locks.
+    lock_timer: Option<SystemTime>,
+}
+
+impl Tetris {
+
+    pub fn new() -> Self {
+        let mut bag = PieceBag::new();
+        let next = (0..NEXT_PREVIEW).map(|_| bag.next()).collect();
+        Tetris {
+            board: [Tetromino::X; (BOARD_WIDTH  * BOARD_HEIGHT) as usize],
+            current: Block::empty(),
+            stopped: false,
+            time: SystemTime::now(),
+            score: 0,
+            level: 0,
+            lines_cleared: 0,
+            high_scores: load_high_scores(),
+            bag,
+            next,
+            hold: None,
+            hold_used: false,
+            lock_timer: None,
+        }
+    }
+
+    pub fn rerun(&mut self) {
+        self.board = [Tetromino::X; (BOARD_WIDTH  * BOARD_HEIGHT) as usize];
+        self.current = Block::empty();
+        self.stopped = false;
+        self.time = SystemTime::now();
+        self.score = 0;
+        self.level = 0;
+        self.lines_cleared = 0;
+        self.high_scores = load_high_scores();
+        self.bag = PieceBag::new();
+        self.next = (0..NEXT_PREVIEW).map(|_| self.bag.next()).collect();
+        self.hold = None;
+        self.hold_used = false;
+        self.lock_timer = None;
+    }
+
+    pub fn tick(&mut self) {
+        if self.current.is_empty() {
+            self.put_block();
+        } else if self.grounded() {
+            if self.lock_timer.is_none() {
+                self.lock_timer = Some(SystemTime::now());
+            }
+            if self.lock_timer.unwrap().elapsed().unwrap() >= LOCK_DELAY {
+                self.block_dropped();
+            }
+        } else {
+            self.lock_timer = None;
+            if self.time.elapsed().unwrap() > Duration::from_millis(gravity_millis(self.level)) {
+                self.try_move(self.current.down());
+                self.time = SystemTime::now();
+            }
+        }
+    }
+
+    /// Whether the current piece can't fall any further.
+    fn grounded(&self) -> bool {
+        !self.fits(self.current.down())
+    }
+
+    pub fn apply(&mut self, command: Command) {
+        if self.stopped || self.current.is_empty() {
+            return;
+        }
+        let moved = match command {
+            Command::Left      => self.try_move(self.current.left()),
+            Command::Right     => self.try_move(self.current.right()),
+            Command::RotateCw  => self.rotate(true),
+            Command::RotateCcw => self.rotate(false),
+            Command::SoftDrop  => { self.down(); false },
+            Command::HardDrop  => { self.drop_down(); false },
+            Command::Hold      => { self.hold_current(); false },
+        };
+        // Lock reset: a successful move or rotation while grounded
+        // restarts the lock delay instead of letting it keep counting down.
+        if moved && self.grounded() {
+            self.lock_timer = Some(SystemTime::now());
+        }
+    }
+
+    /// Swaps the current piece into the hold slot, pulling its
+    /// replacement from the hold (if occupied) or the next queue
+    /// (if not). Disallowed until the current piece locks.
+    fn hold_current(&mut self) {
+        if self.hold_used {
+            return;
+        }
+        let kind = match self.hold {
+            Some(held) => held,
+            None => self.draw_next(),
+        };
+        self.hold = Some(self.current.kind);
+        self.spawn(kind);
+        self.hold_used = true;
+        self.lock_timer = None;
+    }
+
+    /// Spawns `kind` at the top of the board, ending the game and
+    /// recording the high score if it doesn't fit.
+    fn spawn(&mut self, kind: Tetromino) {
+        self.stopped = !self.try_move(Block::new(kind, BOARD_WIDTH / 2, BOARD_HEIGHT - 1));
+        if self.stopped {
+            save_high_score(&mut self.high_scores, self.score);
+        }
+    }
+
+    /// The highest persisted score, or 0 if the table is empty.
+    pub fn best_score(&self) -> u32 {
+        self.high_scores.first().copied().unwrap_or(0)
+    }
+
+    /// The persisted high-score table, highest first, up to `HIGH_SCORE_COUNT` long.
+    pub fn high_scores(&self) -> &[u32] {
+        &self.high_scores
+    }
+
+    /// Pops the next piece off the preview queue, refilling it from the bag.
+    fn draw_next(&mut self) -> Tetromino {
+        let kind = self.next.remove(0);
+        self.next.push(self.bag.next());
+        kind
+    }
+
+    /// Rotates the current block, trying the SRS wall-kick offsets in
+    /// order and applying the first one that lands on a valid position.
+    /// Leaves the piece unrotated if none of them do.
+    fn rotate(&mut self, clockwise: bool) -> bool {
+        let rotated = if clockwise {
+            self.current.rotate_right()
+        } else {
+            self.current.rotate_left()
+        };
+        for (dx, dy) in wall_kicks(self.current.kind, self.current.rotation, rotated.rotation) {
+            if self.try_move(rotated.offset(dx, dy)) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn down(&mut self) {
+        if self.try_move(self.current.down()) {
+            self.lock_timer = None;
+        } else if self.lock_timer.is_none() {
+            self.lock_timer = Some(SystemTime::now());
+        }
+    }
+
+    fn drop_down(&mut self) {
+        while self.current.y > 0 {
+            if !self.try_move(self.current.down()) {
+                break;
+            }
+        }
+        self.block_dropped();
+    }
+
+    fn block_dropped(&mut self) {
+        for i in 0..4 {
+            let (x, y) = self.current.point(i);
+            self.board[index_at(x, y)] = self.current.kind;
+        }
+        self.remove_complete_lines();
+        self.hold_used = false;
+        self.lock_timer = None;
+        if self.current.is_empty() {
+            self.put_block();
+        }
+    }
+
+    fn put_block(&mut self) {
+        let kind = self.draw_next();
+        self.spawn(kind);
+    }
+
+    fn try_move(&mut self, block: Block) -> bool {
+        if !self.fits(block) {
+            return false
+        }
+        self.current = block;
+        true
+    }
+
+    /// Whether `block` is fully in bounds and on empty board cells,
+    /// without mutating `self.current`.
+    fn fits(&self, block: Block) -> bool {
+        for i in 0..4 {
+            let (x, y) = block.point(i);
+            if x < 0 || x >= BOARD_WIDTH || y < 0 || y >= BOARD_HEIGHT {
+                return false
+            }
+            if self.board[index_at(x, y)] != Tetromino::X {
+                return false
+            }
+        }
+        true
+    }
+
+    /// Where the current piece would land if hard-dropped right now.
+    fn ghost(&self) -> Block {
+        let mut ghost = self.current;
+        while self.fits(ghost.down()) {
+            ghost = ghost.down();
+        }
+        ghost
+    }
+
+    fn remove_complete_lines(&mut self) {
+        let mut line_count = 0;
+
+        for y in (0..BOARD_HEIGHT).rev() {
+            let mut complete = true;
+            for x in 0.. BOARD_WIDTH {
+                if self.board[index_at(x, y)] == Tetromino::X {
+                    // traverse the rows and if there is a blank, it cannot be completed
+                    complete = false;
+                    break
+                }
+            }
+            if complete {
+                line_count += 1;
+                // drop the line above the completed line
+                for dy in y..BOARD_HEIGHT - 1 {
+                    for x in 0..BOARD_WIDTH {
+                        // copy from the above line
+                        self.board[index_at(x, dy)] = self.board[index_at(x, dy + 1)];
+                    }
+                }
+            }
+        }
+        self.score += line_count * line_count;
+        self.lines_cleared += line_count;
+        self.level = self.lines_cleared / LINES_PER_LEVEL;
+        self.current = Block::empty();
+    }
+
+    // -- Read-only accessors for the front end --
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// The occupied kind at board cell `(x, y)`, or `Tetromino::X` if empty.
+    pub fn board_cell(&self, x: i32, y: i32) -> Tetromino {
+        self.board[index_at(x, y)]
+    }
+
+    pub fn current_kind(&self) -> Tetromino {
+        self.current.kind
+    }
+
+    /// The four board cells the current piece occupies.
+    pub fn current_cells(&self) -> [(i32, i32); 4] {
+        std::array::from_fn(|i| self.current.point(i))
+    }
+
+    /// The four board cells the current piece would occupy if hard-dropped now.
+    pub fn ghost_cells(&self) -> [(i32, i32); 4] {
+        let ghost = self.ghost();
+        std::array::from_fn(|i| ghost.point(i))
+    }
+
+    /// The upcoming pieces, in spawn order.
+    pub fn next(&self) -> &[Tetromino] {
+        &self.next
+    }
+
+    pub fn hold(&self) -> Option<Tetromino> {
+        self.hold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clears_a_completed_line_and_drops_the_rows_above() {
+        let mut game = Tetris::new();
+        for x in 0..BOARD_WIDTH {
+            game.board[index_at(x, 0)] = Tetromino::T;
+        }
+        game.board[index_at(3, 1)] = Tetromino::I;
+
+        game.remove_complete_lines();
+
+        assert_eq!(game.score, 1);
+        assert_eq!(game.lines_cleared, 1);
+        assert_eq!(game.board[index_at(3, 0)], Tetromino::I);
+        assert_eq!(game.board[index_at(0, 0)], Tetromino::X);
+        assert!(game.current.is_empty());
+    }
+
+    #[test]
+    fn rotation_near_the_wall_is_rescued_by_a_wall_kick() {
+        let mut game = Tetris::new();
+        // A vertical I piece flush against the left wall: rotating it in
+        // place would push its leftmost cell out of bounds, so the SRS
+        // kick table must shift it right before the rotation succeeds.
+        game.current = Block { kind: Tetromino::I, points: Tetromino::I.shape(), x: 1, y: 10, rotation: 0 };
+
+        assert!(game.rotate(true));
+        assert_eq!(game.current.rotation, 1);
+        assert_eq!(game.current.x, 2);
+    }
+}